@@ -19,9 +19,112 @@
 //!
 //! Parameters to pragmatically tweak the core behaviour.
 
-use std::{fs, path::Path};
+use std::{
+    collections::HashMap,
+    fmt::{self, Display, Formatter},
+    fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
 use toml::Value;
 
+/// Prefix for environment-variable configuration overrides, e.g. the
+/// `p2p-addr` key is read from `TRINCI_P2P_ADDR`.
+const ENV_PREFIX: &str = "TRINCI_";
+
+/// Maps a TOML config key to its environment-variable name.
+fn env_key(toml_key: &str) -> String {
+    format!("{}{}", ENV_PREFIX, toml_key.to_uppercase().replace('-', "_"))
+}
+
+/// Overwrites `current` with `file`, then `env`, then `cli`, in that order,
+/// whichever are present. Later layers take precedence over earlier ones.
+fn layer_str(current: &mut String, file: Option<&str>, env: Option<&String>, cli: Option<&str>) {
+    if let Some(value) = file {
+        *current = value.to_owned();
+    }
+    if let Some(value) = env {
+        *current = value.to_owned();
+    }
+    if let Some(value) = cli {
+        *current = value.to_owned();
+    }
+}
+
+/// Same as `layer_str` but for optional string fields.
+fn layer_opt_str(
+    current: &mut Option<String>,
+    file: Option<&str>,
+    env: Option<&String>,
+    cli: Option<&str>,
+) {
+    if let Some(value) = file {
+        *current = Some(value.to_owned());
+    }
+    if let Some(value) = env {
+        *current = Some(value.to_owned());
+    }
+    if let Some(value) = cli {
+        *current = Some(value.to_owned());
+    }
+}
+
+/// Same as `layer_str` but for a boolean flag, where the CLI layer is a
+/// presence switch rather than a value.
+fn layer_bool(current: &mut bool, file: Option<bool>, env: Option<&String>, cli_present: bool) {
+    if let Some(value) = file {
+        *current = value;
+    }
+    if let Some(value) = env {
+        *current = value == "1" || value.eq_ignore_ascii_case("true");
+    }
+    if cli_present {
+        *current = true;
+    }
+}
+
+/// Same as `layer_str` but for any integer field parsed from its textual form.
+fn layer_num<T: FromStr>(current: &mut T, file: Option<i64>, env: Option<&String>, cli: Option<&str>) {
+    if let Some(value) = file {
+        if let Ok(parsed) = value.to_string().parse() {
+            *current = parsed;
+        }
+    }
+    if let Some(value) = env {
+        if let Ok(parsed) = value.parse() {
+            *current = parsed;
+        }
+    }
+    if let Some(value) = cli {
+        if let Ok(parsed) = value.parse() {
+            *current = parsed;
+        }
+    }
+}
+
+/// Same as `layer_str` but for a list, each layer fully replacing the
+/// previous one when present.
+fn layer_vec(
+    current: &mut Vec<String>,
+    file: Option<Vec<String>>,
+    env: Option<&String>,
+    cli: Option<Vec<String>>,
+) {
+    if let Some(value) = file {
+        *current = value;
+    }
+    if let Some(value) = env {
+        *current = value
+            .split(',')
+            .map(|item| item.trim().to_owned())
+            .filter(|item| !item.is_empty())
+            .collect();
+    }
+    if let Some(value) = cli {
+        *current = value;
+    }
+}
+
 /// TODO: add to configuration??? Maybe yes... maybe not.
 pub const SERVICE_ACCOUNT_ID: &str = "QmfZy5bvk7a3DQAjCbGNtmrPXWkyVvPrdnZMyBZ5q5ieKG";
 
@@ -64,6 +167,24 @@ pub const DEFAULT_DB_PATH: &str = "db";
 /// Default smart contracts cache size.
 pub const DEFAULT_WM_CACHE_MAX: usize = 10;
 
+/// Resolves the P2P advertise address from a raw TOML/CLI value.
+///
+/// If `raw` already has a `host:port` form it is used verbatim, otherwise it
+/// is taken as a bare port and combined with the P2P bind address.
+fn resolve_p2p_advertise_addr(bind_addr: &str, raw: &str) -> String {
+    if raw.contains(':') {
+        raw.to_owned()
+    } else {
+        format!("{}:{}", bind_addr, raw)
+    }
+}
+
+/// Parses a config file's contents as TOML, returning the parse error
+/// message on failure so the caller can decide how to report it.
+fn parse_config_toml(content: &str) -> Result<Value, String> {
+    content.parse::<Value>().map_err(|err| err.to_string())
+}
+
 /// Core configuration structure.
 #[derive(PartialEq, Debug, Clone)]
 pub struct Config {
@@ -85,18 +206,38 @@ pub struct Config {
     pub rest_addr: String,
     /// Http service port.
     pub rest_port: u16,
+    /// Optional TLS certificate file path for the REST service. When set
+    /// together with `rest_tls_key_path`, the REST service is served over
+    /// HTTPS.
+    pub rest_tls_cert_path: Option<String>,
+    /// Optional TLS private key file path for the REST service.
+    pub rest_tls_key_path: Option<String>,
+    /// Optional JWT secret file path guarding the REST service. When set,
+    /// requests lacking a valid bearer token signed with this secret are
+    /// rejected.
+    pub rest_jwt_secret_path: Option<String>,
     /// Bridge service address.
     pub bridge_addr: String,
     /// Bridge service port.
     pub bridge_port: u16,
     /// P2P service address.
     pub p2p_addr: String,
+    /// Publicly reachable P2P address (`host:port`) that peers should use to
+    /// reach this node, e.g. when the node sits behind NAT or port-forwarding
+    /// and `p2p_addr` is not routable. Falls back to `p2p_addr` when unset.
+    pub p2p_advertise_addr: Option<String>,
+    /// List of peers (`host:port`) the P2P layer dials on startup to join the
+    /// network.
+    pub p2p_bootstrap_peers: Vec<String>,
     /// Blockchain database folder path.
     pub db_path: String,
     /// Bootstrap wasm file path.
     pub bootstrap_path: String,
     /// WASM machine max cache size.
     pub wm_cache_max: usize,
+    /// Boot all services then terminate without producing blocks.
+    /// Only meant to be toggled via the hidden `--immediate-shutdown` test flag.
+    pub immediate_shutdown: bool,
 }
 
 impl Default for Config {
@@ -110,263 +251,483 @@ impl Default for Config {
             block_timeout: DEFAULT_BLOCK_TIMEOUT,
             rest_addr: DEFAULT_HTTP_ADDR.to_string(),
             rest_port: DEFAULT_HTTP_PORT,
+            rest_tls_cert_path: None,
+            rest_tls_key_path: None,
+            rest_jwt_secret_path: None,
             bridge_addr: DEFAULT_BRIDGE_ADDR.to_string(),
             bridge_port: DEFAULT_BRIDGE_PORT,
             p2p_addr: DEFAULT_P2P_ADDR.to_string(),
+            p2p_advertise_addr: None,
+            p2p_bootstrap_peers: Vec::new(),
             db_path: DEFAULT_DB_PATH.to_string(),
             bootstrap_path: DEFAULT_BOOTSTRAP_PATH.to_string(),
             wm_cache_max: DEFAULT_WM_CACHE_MAX,
+            immediate_shutdown: false,
         }
     }
 }
 
-impl Config {
-    /// Instance a new configuration using options found in the config file.
-    /// If a config option is not found in the file, then the default one is used.
-    pub fn from_file<P: AsRef<Path>>(path: P) -> Option<Self> {
-        let mut config = Self::default();
-
-        let map = match fs::read_to_string(path) {
-            Ok(content) => match content.parse::<Value>() {
-                Ok(map) => map,
-                Err(_err) => {
-                    error!("Error: bad config file format");
-                    return None;
-                }
-            },
-            Err(_err) => {
-                warn!("Warning: config file not found, using default options");
-                return Some(config);
-            }
-        };
-
-        if let Some(value) = map.get("validator").and_then(|value| value.as_bool()) {
-            config.validator = value;
-        }
-        if let Some(value) = map.get("log-level").and_then(|value| value.as_str()) {
-            config.log_level = value.to_owned()
-        }
-        if let Some(value) = map.get("keypair-path").and_then(|value| value.as_str()) {
-            config.keypair_path = Some(value.to_owned())
-        }
-        if let Some(value) = map.get("network").and_then(|value| value.as_str()) {
-            config.network = value.to_owned();
-        }
-        if let Some(value) = map.get("rest-addr").and_then(|value| value.as_str()) {
-            config.rest_addr = value.to_owned();
-        }
-        if let Some(value) = map.get("rest-port").and_then(|value| value.as_integer()) {
-            config.rest_port = value as u16;
+impl Display for Config {
+    /// Serializes the configuration as TOML, matching the keys expected by
+    /// `Config::resolve`. Used by the `--dump-config` test flag.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "validator = {}\n\
+            log-level = '{}'\n\
+            network = '{}'\n\
+            block-threshold = {}\n\
+            block-timeout = {}\n\
+            rest-addr = '{}'\n\
+            rest-port = {}\n\
+            bridge-addr = '{}'\n\
+            bridge-port = {}\n\
+            p2p-addr = '{}'\n\
+            db-path = '{}'\n\
+            bootstrap-path = '{}'\n\
+            wm-cache-max = {}",
+            self.validator,
+            self.log_level,
+            self.network,
+            self.block_threshold,
+            self.block_timeout,
+            self.rest_addr,
+            self.rest_port,
+            self.bridge_addr,
+            self.bridge_port,
+            self.p2p_addr,
+            self.db_path,
+            self.bootstrap_path,
+            self.wm_cache_max
+        )?;
+        if let Some(path) = &self.keypair_path {
+            write!(f, "\nkeypair-path = '{}'", path)?;
         }
-        if let Some(value) = map.get("bridge-addr").and_then(|value| value.as_str()) {
-            config.bridge_addr = value.to_owned();
+        if let Some(cert_path) = &self.rest_tls_cert_path {
+            write!(f, "\nrest-tls-cert = '{}'", cert_path)?;
         }
-        if let Some(value) = map.get("bridge-port").and_then(|value| value.as_integer()) {
-            config.bridge_port = value as u16;
+        if let Some(key_path) = &self.rest_tls_key_path {
+            write!(f, "\nrest-tls-key = '{}'", key_path)?;
         }
-        if let Some(value) = map.get("p2p-addr").and_then(|value| value.as_str()) {
-            config.p2p_addr = value.to_owned();
+        if let Some(secret_path) = &self.rest_jwt_secret_path {
+            write!(f, "\nrest-jwt-secret = '{}'", secret_path)?;
         }
-        if let Some(value) = map
-            .get("block-threshold")
-            .and_then(|value| value.as_integer())
-        {
-            config.block_threshold = value as usize;
+        if let Some(advertise_addr) = &self.p2p_advertise_addr {
+            write!(f, "\np2p-advertise-addr = '{}'", advertise_addr)?;
         }
-        if let Some(value) = map
-            .get("block-timeout")
-            .and_then(|value| value.as_integer())
-        {
-            config.block_timeout = value as u16;
+        if !self.p2p_bootstrap_peers.is_empty() {
+            let peers = self
+                .p2p_bootstrap_peers
+                .iter()
+                .map(|peer| format!("'{}'", peer))
+                .collect::<Vec<_>>()
+                .join(", ");
+            write!(f, "\np2p-bootstrap-peers = [{}]", peers)?;
         }
-        if let Some(value) = map.get("db-path").and_then(|value| value.as_str()) {
-            config.db_path = value.to_owned();
-        }
-        if let Some(value) = map.get("bootstrap-path").and_then(|value| value.as_str()) {
-            config.bootstrap_path = value.to_owned();
-        }
-        if let Some(value) = map.get("wm-cache-max").and_then(|value| value.as_integer()) {
-            config.wm_cache_max = value as usize;
+        Ok(())
+    }
+}
+
+impl Config {
+    /// Resolves a `Config` from four layers, each overriding the previous one
+    /// field-by-field: built-in defaults, an optional TOML `file`, `TRINCI_`-
+    /// prefixed entries in `env`, then parsed CLI `matches`. A missing `file`
+    /// simply means that layer is skipped, but a `file` that exists and fails
+    /// to parse as TOML is treated as fatal.
+    pub fn resolve(file: Option<&Path>, env: &HashMap<String, String>, matches: &clap::ArgMatches) -> Self {
+        let mut config = Self::default();
+
+        let file_map = file
+            .and_then(|path| fs::read_to_string(path).ok())
+            .map(|content| {
+                parse_config_toml(&content).unwrap_or_else(|err| {
+                    error!("Error: bad config file format: {}", err);
+                    std::process::exit(1);
+                })
+            });
+        let file_str = |key: &str| file_map.as_ref().and_then(|map| map.get(key)).and_then(Value::as_str);
+        let file_bool = |key: &str| file_map.as_ref().and_then(|map| map.get(key)).and_then(Value::as_bool);
+        let file_int = |key: &str| file_map.as_ref().and_then(|map| map.get(key)).and_then(Value::as_integer);
+        let file_peers = |key: &str| -> Option<Vec<String>> {
+            file_map
+                .as_ref()
+                .and_then(|map| map.get(key))
+                .and_then(Value::as_array)
+                .map(|array| {
+                    array
+                        .iter()
+                        .filter_map(|value| match value.as_str() {
+                            Some(peer) => Some(peer.to_owned()),
+                            None => {
+                                warn!("Warning: skipping malformed {} entry", key);
+                                None
+                            }
+                        })
+                        .collect()
+                })
+        };
+        let env = |key: &str| env.get(&env_key(key));
+
+        layer_bool(&mut config.validator, file_bool("validator"), env("validator"), matches.is_present("validator"));
+        layer_str(&mut config.log_level, file_str("log-level"), env("log-level"), matches.value_of("log-level"));
+        layer_opt_str(&mut config.keypair_path, file_str("keypair-path"), env("keypair-path"), matches.value_of("keypair-path"));
+        layer_str(&mut config.network, file_str("network"), env("network"), matches.value_of("network"));
+        layer_num(&mut config.block_threshold, file_int("block-threshold"), env("block-threshold"), matches.value_of("block-threshold"));
+        layer_num(&mut config.block_timeout, file_int("block-timeout"), env("block-timeout"), matches.value_of("block-timeout"));
+        layer_str(&mut config.rest_addr, file_str("rest-addr"), env("rest-addr"), matches.value_of("http-addr"));
+        layer_num(&mut config.rest_port, file_int("rest-port"), env("rest-port"), matches.value_of("http-port"));
+        layer_opt_str(&mut config.rest_tls_cert_path, file_str("rest-tls-cert"), env("rest-tls-cert"), matches.value_of("rest-tls-cert"));
+        layer_opt_str(&mut config.rest_tls_key_path, file_str("rest-tls-key"), env("rest-tls-key"), matches.value_of("rest-tls-key"));
+        layer_opt_str(&mut config.rest_jwt_secret_path, file_str("rest-jwt-secret"), env("rest-jwt-secret"), matches.value_of("rest-jwt-secret"));
+        layer_str(&mut config.bridge_addr, file_str("bridge-addr"), env("bridge-addr"), matches.value_of("bridge-addr"));
+        layer_num(&mut config.bridge_port, file_int("bridge-port"), env("bridge-port"), matches.value_of("bridge-port"));
+        layer_str(&mut config.p2p_addr, file_str("p2p-addr"), env("p2p-addr"), matches.value_of("p2p-addr"));
+        layer_opt_str(
+            &mut config.p2p_advertise_addr,
+            file_str("p2p-advertise-addr"),
+            env("p2p-advertise-addr"),
+            matches.value_of("p2p-advertise-addr"),
+        );
+        if let Some(advertise_addr) = &config.p2p_advertise_addr {
+            if !advertise_addr.contains(':') {
+                config.p2p_advertise_addr =
+                    Some(resolve_p2p_advertise_addr(&config.p2p_addr, advertise_addr));
+            }
         }
+        layer_vec(
+            &mut config.p2p_bootstrap_peers,
+            file_peers("p2p-bootstrap-peers"),
+            env("p2p-bootstrap-peers"),
+            matches
+                .values_of("p2p-bootstrap-peer")
+                .map(|values| values.map(str::to_owned).collect()),
+        );
+        layer_str(&mut config.db_path, file_str("db-path"), env("db-path"), matches.value_of("db-path"));
+        layer_str(&mut config.bootstrap_path, file_str("bootstrap-path"), env("bootstrap-path"), matches.value_of("bootstrap-path"));
+        layer_num(&mut config.wm_cache_max, file_int("wm-cache-max"), env("wm-cache-max"), matches.value_of("wm-cache-max"));
 
-        Some(config)
+        config
     }
 }
 
-pub fn create_app_config() -> Config {
-    let matches = clap::App::new("T2 Node")
+/// Blockchain maintenance action requested on the command line, each carrying
+/// the resolved node `Config` it was parsed alongside.
+pub enum Command {
+    /// Run the node.
+    Run(Config),
+    /// Import a blockchain from a file into the local database.
+    Import { config: Config, src: PathBuf },
+    /// Export the local database's blockchain to a file.
+    Export {
+        config: Config,
+        dst: PathBuf,
+        from: u64,
+        to: u64,
+    },
+    /// Revert the local database to a given block height.
+    Revert { config: Config, to_height: u64 },
+}
+
+/// Builds the set of CLI arguments shared by `run` and every maintenance
+/// subcommand (config file overrides). Marked `global` so each subcommand
+/// inherits them regardless of where on the command line they appear.
+fn shared_args<'a, 'b>() -> Vec<clap::Arg<'a, 'b>> {
+    vec![
+        clap::Arg::with_name("config")
+            .short("c")
+            .long("config")
+            .help("Configuration file (default 'config.toml')")
+            .value_name("CONFIG")
+            .required(false)
+            .global(true),
+        clap::Arg::with_name("validator")
+            .long("validator")
+            .help("Start node as a validator")
+            .global(true),
+        clap::Arg::with_name("log-level")
+            .long("log-level")
+            .help("Logger level (default 'info')")
+            .value_name("LEVEL")
+            .required(false)
+            .possible_values(&["off", "error", "warn", "info", "debug", "trace"])
+            .global(true),
+        clap::Arg::with_name("keypair-path")
+            .long("keypair-path")
+            .help("Node keypair file path")
+            .value_name("PATH")
+            .required(false)
+            .global(true),
+        clap::Arg::with_name("network")
+            .long("network")
+            .help("Blockchain network identifier (default 'skynet')")
+            .value_name("NETWORK-NAME")
+            .required(false)
+            .global(true),
+        clap::Arg::with_name("block-threshold")
+            .long("block-threshold")
+            .help("Max number of transactions within a block (default '42')")
+            .value_name("COUNT")
+            .required(false)
+            .global(true),
+        clap::Arg::with_name("block-timeout")
+            .long("block-timeout")
+            .help("Max number of seconds to trigger block creation (default '3')")
+            .value_name("SECONDS")
+            .required(false)
+            .global(true),
+        clap::Arg::with_name("db-path")
+            .long("db-path")
+            .help("Database folder (default 'db')")
+            .value_name("PATH")
+            .required(false)
+            .global(true),
+        clap::Arg::with_name("bootstrap-path")
+            .long("bootstrap-path")
+            .help("Bootstrap wasm file path (default 'data/bootstrap.wasm')")
+            .value_name("PATH")
+            .required(false)
+            .global(true),
+        clap::Arg::with_name("http-addr")
+            .long("http-addr")
+            .help("Http service binding address (default '127.0.0.1')")
+            .value_name("ADDRESS")
+            .required(false)
+            .global(true),
+        clap::Arg::with_name("http-port")
+            .long("http-port")
+            .help("Http service listening port (default '8000')")
+            .value_name("PORT")
+            .required(false)
+            .global(true),
+        clap::Arg::with_name("rest-tls-cert")
+            .long("rest-tls-cert")
+            .help("TLS certificate file path for the REST service, serves over HTTPS when set together with 'rest-tls-key'")
+            .value_name("PATH")
+            .required(false)
+            .global(true),
+        clap::Arg::with_name("rest-tls-key")
+            .long("rest-tls-key")
+            .help("TLS private key file path for the REST service")
+            .value_name("PATH")
+            .required(false)
+            .global(true),
+        clap::Arg::with_name("rest-jwt-secret")
+            .long("rest-jwt-secret")
+            .help("JWT secret file path guarding the REST service with bearer token authentication")
+            .value_name("PATH")
+            .required(false)
+            .global(true),
+        clap::Arg::with_name("bridge-addr")
+            .long("bridge-addr")
+            .help("Bridge service binding address (default '127.0.0.1')")
+            .value_name("ADDRESS")
+            .required(false)
+            .global(true),
+        clap::Arg::with_name("bridge-port")
+            .long("bridge-port")
+            .help("Bridge service listening port (default '8001')")
+            .value_name("PORT")
+            .required(false)
+            .global(true),
+        clap::Arg::with_name("p2p-addr")
+            .long("p2p-addr")
+            .help("Peer2Peer service binding address (default '127.0.0.1')")
+            .value_name("ADDRESS")
+            .required(false)
+            .global(true),
+        clap::Arg::with_name("p2p-advertise-addr")
+            .long("p2p-advertise-addr")
+            .help(
+                "Publicly reachable P2P address peers should use to reach this node, \
+                as 'host:port' or just 'port' to keep the bind address (default: none, \
+                falls back to 'p2p-addr')",
+            )
+            .value_name("ADDRESS")
+            .required(false)
+            .global(true),
+        clap::Arg::with_name("p2p-bootstrap-peer")
+            .long("p2p-bootstrap-peer")
+            .help("Bootstrap peer address ('host:port') to dial on startup, may be repeated")
+            .value_name("ADDRESS")
+            .multiple(true)
+            .number_of_values(1)
+            .required(false)
+            .global(true),
+        clap::Arg::with_name("wm-cache-max")
+            .long("wm-cache-max")
+            .help("WASM machine max cache size (default '10')")
+            .value_name("COUNT")
+            .required(false)
+            .global(true),
+        clap::Arg::with_name("dump-config")
+            .long("dump-config")
+            .help("Dump the fully merged configuration as TOML to stdout and exit")
+            .hidden(true)
+            .global(true),
+        clap::Arg::with_name("immediate-shutdown")
+            .long("immediate-shutdown")
+            .help("Boot all services then terminate cleanly without producing blocks")
+            .hidden(true)
+            .global(true),
+    ]
+}
+
+/// Merges defaults, the config file, `TRINCI_`-prefixed environment
+/// variables and the shared CLI overrides into a `Config` via
+/// `Config::resolve`, honouring the `--dump-config` and `--immediate-shutdown`
+/// test flags.
+fn resolve_config(matches: &clap::ArgMatches) -> Config {
+    let config_file = matches.value_of("config").unwrap_or(DEFAULT_CONFIG_FILE);
+    let env: HashMap<String, String> = std::env::vars().collect();
+    let mut config = Config::resolve(Some(Path::new(config_file)), &env, matches);
+
+    if matches.is_present("immediate-shutdown") {
+        config.immediate_shutdown = true;
+    }
+
+    if matches.is_present("dump-config") {
+        println!("{}", config);
+        std::process::exit(0);
+    }
+
+    config
+}
+
+/// Clap argument validator rejecting values that don't parse as a `u64`
+/// block height, so malformed operator input is reported as a usage error
+/// instead of panicking later on.
+fn validate_u64(value: String) -> Result<(), String> {
+    value
+        .parse::<u64>()
+        .map(|_| ())
+        .map_err(|err| format!("'{}' is not a valid block height: {}", value, err))
+}
+
+/// Builds the CLI `App`, with the `run`/`import`/`export`/`revert`
+/// subcommands, without parsing `std::env::args()`. Split out from
+/// `create_app_config` so tests can feed it argument vectors directly.
+fn build_app() -> clap::App<'static, 'static> {
+    clap::App::new("T2 Node")
         .version(clap::crate_version!())
         .author(clap::crate_authors!())
         .about(clap::crate_description!())
-        .arg(
-            clap::Arg::with_name("config")
-                .short("c")
-                .long("config")
-                .help("Configuration file (default 'config.toml')")
-                .value_name("CONFIG")
-                .required(false),
-        )
-        .arg(
-            clap::Arg::with_name("validator")
-                .long("validator")
-                .help("Start node as a validator"),
-        )
-        .arg(
-            clap::Arg::with_name("log-level")
-                .long("log-level")
-                .help(&format!("Logger level (default '{}')", DEFAULT_LOG_LEVEL))
-                .value_name("LEVEL")
-                .required(false)
-                .possible_values(&["off", "error", "warn", "info", "debug", "trace"]),
-        )
-        .arg(
-            clap::Arg::with_name("network")
-                .long("network")
-                .help(&format!(
-                    "Blockchain network identifier (default '{}')",
-                    DEFAULT_NETWORK_ID
-                ))
-                .value_name("NETWORK-NAME")
-                .required(false),
+        .args(&shared_args())
+        .subcommand(clap::SubCommand::with_name("run").about("Run the node (default)"))
+        .subcommand(
+            clap::SubCommand::with_name("import")
+                .about("Import a blockchain from a file into the local database")
+                .arg(
+                    clap::Arg::with_name("src")
+                        .long("src")
+                        .help("Source file to import the blockchain from")
+                        .value_name("PATH")
+                        .required(true),
+                ),
         )
-        .arg(
-            clap::Arg::with_name("db-path")
-                .long("db-path")
-                .help(&format!("Database folder (default '{}')", DEFAULT_DB_PATH))
-                .value_name("PATH")
-                .required(false),
+        .subcommand(
+            clap::SubCommand::with_name("export")
+                .about("Export the local database's blockchain to a file")
+                .arg(
+                    clap::Arg::with_name("dst")
+                        .long("dst")
+                        .help("Destination file to export the blockchain to")
+                        .value_name("PATH")
+                        .required(true),
+                )
+                .arg(
+                    clap::Arg::with_name("from")
+                        .long("from")
+                        .help("First block height to export (default 0)")
+                        .value_name("HEIGHT")
+                        .required(false)
+                        .validator(validate_u64),
+                )
+                .arg(
+                    clap::Arg::with_name("to")
+                        .long("to")
+                        .help("Last block height to export")
+                        .value_name("HEIGHT")
+                        .required(true)
+                        .validator(validate_u64),
+                ),
         )
-        .arg(
-            clap::Arg::with_name("bootstrap-path")
-                .long("bootstrap-path")
-                .help(&format!(
-                    "Bootstrap wasm file path (default '{}')",
-                    DEFAULT_BOOTSTRAP_PATH
-                ))
-                .value_name("PATH")
-                .required(false),
+        .subcommand(
+            clap::SubCommand::with_name("revert")
+                .about("Revert the local database to a given block height")
+                .arg(
+                    clap::Arg::with_name("to-height")
+                        .long("to-height")
+                        .help("Block height to revert the database to")
+                        .value_name("HEIGHT")
+                        .required(true)
+                        .validator(validate_u64),
+                ),
         )
-        .arg(
-            clap::Arg::with_name("http-addr")
-                .long("http-addr")
-                .help("Http service binding address (default '127.0.0.1')")
-                .value_name("ADDRESS")
-                .required(false),
-        )
-        .arg(
-            clap::Arg::with_name("http-port")
-                .long("http-port")
-                .help("Http service listening port (default '8000')")
-                .value_name("PORT")
-                .required(false),
-        )
-        .arg(
-            clap::Arg::with_name("bridge-addr")
-                .long("bridge-addr")
-                .help("Bridge service binding address (default '127.0.0.1')")
-                .value_name("ADDRESS")
-                .required(false),
-        )
-        .arg(
-            clap::Arg::with_name("bridge-port")
-                .long("bridge-port")
-                .help("Bridge service listening port (default '8001')")
-                .value_name("PORT")
-                .required(false),
-        )
-        .arg(
-            clap::Arg::with_name("p2p-addr")
-                .long("p2p-addr")
-                .help("Peer2Peer service binding address (default '127.0.0.1')")
-                .value_name("ADDRESS")
-                .required(false),
-        )
-        .get_matches();
-
-    let config_file = matches.value_of("config").unwrap_or(DEFAULT_CONFIG_FILE);
-    let mut config = Config::from_file(config_file).expect("Bad config file");
+}
 
-    // Tweak configuration using command line arguments.
-    if matches.is_present("validator") {
-        config.validator = true;
-    }
-    if let Some(value) = matches.value_of("log-level") {
-        config.log_level = value.to_owned();
-    }
-    if let Some(value) = matches.value_of("network") {
-        config.network = value.to_owned();
-    }
-    if let Some(value) = matches.value_of("db-path") {
-        config.db_path = value.to_owned();
-    }
-    if let Some(value) = matches.value_of("boot-path") {
-        config.bootstrap_path = value.to_owned();
-    }
-    if let Some(value) = matches.value_of("http-addr") {
-        config.rest_addr = value.to_owned();
-    }
-    if let Some(value) = matches
-        .value_of("http-port")
-        .and_then(|value| value.parse::<u16>().ok())
-    {
-        config.rest_port = value;
-    }
-    if let Some(value) = matches.value_of("bridge-addr") {
-        config.bridge_addr = value.to_owned();
-    }
-    if let Some(value) = matches
-        .value_of("bridge-port")
-        .and_then(|value| value.parse::<u16>().ok())
-    {
-        config.bridge_port = value;
-    }
-    if let Some(value) = matches.value_of("p2p-addr") {
-        config.p2p_addr = value.to_owned();
+/// Dispatches already-parsed `matches` to the requested `Command`, resolving
+/// each subcommand's `Config` along the way.
+fn command_from_matches(matches: &clap::ArgMatches) -> Command {
+    match matches.subcommand() {
+        ("import", Some(sub_matches)) => {
+            let config = resolve_config(sub_matches);
+            let src = PathBuf::from(
+                sub_matches
+                    .value_of("src")
+                    .expect("--src is a required argument"),
+            );
+            Command::Import { config, src }
+        }
+        ("export", Some(sub_matches)) => {
+            let config = resolve_config(sub_matches);
+            let dst = PathBuf::from(
+                sub_matches
+                    .value_of("dst")
+                    .expect("--dst is a required argument"),
+            );
+            // Already checked by `validate_u64`.
+            let from = sub_matches
+                .value_of("from")
+                .map(|value| value.parse::<u64>().unwrap())
+                .unwrap_or(0);
+            let to = sub_matches
+                .value_of("to")
+                .expect("--to is a required argument")
+                .parse::<u64>()
+                .unwrap();
+            Command::Export {
+                config,
+                dst,
+                from,
+                to,
+            }
+        }
+        ("revert", Some(sub_matches)) => {
+            let config = resolve_config(sub_matches);
+            // Already checked by `validate_u64`.
+            let to_height = sub_matches
+                .value_of("to-height")
+                .expect("--to-height is a required argument")
+                .parse::<u64>()
+                .unwrap();
+            Command::Revert { config, to_height }
+        }
+        ("run", Some(sub_matches)) => Command::Run(resolve_config(sub_matches)),
+        _ => Command::Run(resolve_config(matches)),
     }
-    config
+}
+
+pub fn create_app_config() -> Command {
+    let matches = build_app().get_matches();
+    command_from_matches(&matches)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::fmt::{self, Display, Formatter};
     use std::io::Write;
     use tempfile::NamedTempFile;
 
-    impl Display for Config {
-        fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-            write!(
-                f,
-                "validator = {}\n\
-                log-level = '{}'\n\
-                network = '{}'\n\
-                block-threshold = {}\n\
-                block-timeout = {}\n\
-                rest-addr = '{}'\n\
-                rest-port = {}\n\
-                bridge-addr = '{}'\n\
-                bridge-port = {}\n\
-                p2p-addr = '{}'\n\
-                db-path = '{}'\n\
-                bootstrap-path = '{}'\n\
-                wm-cache-max = {}",
-                self.validator,
-                self.log_level,
-                self.network,
-                self.block_threshold,
-                self.block_timeout,
-                self.rest_addr,
-                self.rest_port,
-                self.bridge_addr,
-                self.bridge_port,
-                self.p2p_addr,
-                self.db_path,
-                self.bootstrap_path,
-                self.wm_cache_max
-            )
-        }
-    }
-
     fn create_test_config() -> Config {
         Config {
             log_level: "debug".to_string(),
@@ -377,24 +738,235 @@ mod tests {
             block_timeout: 4321,
             rest_addr: "1.2.3.4".to_string(),
             rest_port: 123,
+            rest_tls_cert_path: None,
+            rest_tls_key_path: None,
+            rest_jwt_secret_path: None,
             bridge_addr: "5.6.7.8".to_string(),
             bridge_port: 987,
             p2p_addr: "9.1.2.3".to_string(),
+            p2p_advertise_addr: None,
+            p2p_bootstrap_peers: Vec::new(),
             db_path: "dummy/db/path".to_string(),
             bootstrap_path: "dummy/boot/path".to_string(),
             wm_cache_max: 42,
+            immediate_shutdown: false,
         }
     }
 
     #[test]
-    fn from_file() {
+    fn resolve_from_file() {
         let default_config = create_test_config();
         let mut file = NamedTempFile::new().unwrap();
         let _ = writeln!(&mut file, "{}", default_config);
-        let filename = file.path().as_os_str().to_string_lossy().to_string();
 
-        let config = Config::from_file(filename).unwrap();
+        let config = Config::resolve(
+            Some(file.path()),
+            &HashMap::new(),
+            &clap::ArgMatches::default(),
+        );
 
         assert_eq!(config, default_config);
     }
+
+    #[test]
+    fn dump_config_flag_is_recognized() {
+        let app = clap::App::new("test").args(&shared_args());
+        let matches = app.get_matches_from(vec!["test", "--dump-config"]);
+
+        assert!(matches.is_present("dump-config"));
+    }
+
+    #[test]
+    fn immediate_shutdown_flag_sets_config() {
+        let app = clap::App::new("test").args(&shared_args());
+        let matches = app.get_matches_from(vec!["test", "--immediate-shutdown"]);
+
+        let config = resolve_config(&matches);
+
+        assert!(config.immediate_shutdown);
+    }
+
+    #[test]
+    fn p2p_advertise_addr_bare_port_uses_bind_host() {
+        assert_eq!(
+            resolve_p2p_advertise_addr("1.2.3.4", "9000"),
+            "1.2.3.4:9000"
+        );
+    }
+
+    #[test]
+    fn p2p_advertise_addr_full_address_is_used_verbatim() {
+        assert_eq!(
+            resolve_p2p_advertise_addr("1.2.3.4", "5.6.7.8:9000"),
+            "5.6.7.8:9000"
+        );
+    }
+
+    #[test]
+    fn malformed_bootstrap_peer_entries_are_skipped() {
+        let mut file = NamedTempFile::new().unwrap();
+        let _ = writeln!(
+            &mut file,
+            "p2p-bootstrap-peers = ['1.2.3.4:1000', 42, '5.6.7.8:2000']"
+        );
+
+        let config = Config::resolve(
+            Some(file.path()),
+            &HashMap::new(),
+            &clap::ArgMatches::default(),
+        );
+
+        assert_eq!(
+            config.p2p_bootstrap_peers,
+            vec!["1.2.3.4:1000".to_string(), "5.6.7.8:2000".to_string()]
+        );
+    }
+
+    #[test]
+    fn rest_tls_and_jwt_fields_round_trip() {
+        let mut default_config = create_test_config();
+        default_config.rest_tls_cert_path = Some("cert.pem".to_string());
+        default_config.rest_tls_key_path = Some("key.pem".to_string());
+        default_config.rest_jwt_secret_path = Some("secret.bin".to_string());
+
+        let mut file = NamedTempFile::new().unwrap();
+        let _ = writeln!(&mut file, "{}", default_config);
+
+        let config = Config::resolve(
+            Some(file.path()),
+            &HashMap::new(),
+            &clap::ArgMatches::default(),
+        );
+
+        assert_eq!(config, default_config);
+    }
+
+    #[test]
+    fn parse_config_toml_rejects_malformed_content() {
+        assert!(parse_config_toml("not = [valid toml").is_err());
+    }
+
+    #[test]
+    fn resolve_precedence_cli_overrides_env_overrides_file() {
+        let default_config = create_test_config();
+        let mut file = NamedTempFile::new().unwrap();
+        let _ = writeln!(&mut file, "{}", default_config);
+
+        let mut env = HashMap::new();
+        env.insert(env_key("block-threshold"), "111".to_string());
+        env.insert(env_key("network"), "env_network".to_string());
+
+        let app = clap::App::new("test").args(&shared_args());
+        let matches = app.get_matches_from(vec!["test", "--network", "cli_network"]);
+
+        let config = Config::resolve(Some(file.path()), &env, &matches);
+
+        // CLI overrides env, which overrides the file, which overrides defaults.
+        assert_eq!(config.network, "cli_network");
+        assert_eq!(config.block_threshold, 111);
+        assert_eq!(config.block_timeout, default_config.block_timeout);
+    }
+
+    #[test]
+    fn bootstrap_path_cli_flag_maps_to_bootstrap_path_field() {
+        let app = clap::App::new("test").args(&shared_args());
+        let matches =
+            app.get_matches_from(vec!["test", "--bootstrap-path", "/custom/boot.wasm"]);
+
+        let config = Config::resolve(None, &HashMap::new(), &matches);
+
+        assert_eq!(config.bootstrap_path, "/custom/boot.wasm");
+    }
+
+    #[test]
+    fn block_threshold_timeout_and_wm_cache_max_cli_flags_are_applied() {
+        let app = clap::App::new("test").args(&shared_args());
+        let matches = app.get_matches_from(vec![
+            "test",
+            "--block-threshold",
+            "222",
+            "--block-timeout",
+            "333",
+            "--wm-cache-max",
+            "444",
+        ]);
+
+        let config = Config::resolve(None, &HashMap::new(), &matches);
+
+        assert_eq!(config.block_threshold, 222);
+        assert_eq!(config.block_timeout, 333);
+        assert_eq!(config.wm_cache_max, 444);
+    }
+
+    #[test]
+    fn validate_u64_accepts_numeric_value() {
+        assert!(validate_u64("123".to_string()).is_ok());
+    }
+
+    #[test]
+    fn validate_u64_rejects_non_numeric_value() {
+        assert!(validate_u64("not-a-number".to_string()).is_err());
+    }
+
+    #[test]
+    fn import_subcommand_builds_command_import() {
+        let matches = build_app().get_matches_from(vec!["t2node", "import", "--src", "chain.dat"]);
+
+        match command_from_matches(&matches) {
+            Command::Import { src, .. } => assert_eq!(src, PathBuf::from("chain.dat")),
+            _ => panic!("expected Command::Import"),
+        }
+    }
+
+    #[test]
+    fn export_subcommand_builds_command_export_with_default_from() {
+        let matches = build_app().get_matches_from(vec![
+            "t2node", "export", "--dst", "out.dat", "--to", "100",
+        ]);
+
+        match command_from_matches(&matches) {
+            Command::Export { dst, from, to, .. } => {
+                assert_eq!(dst, PathBuf::from("out.dat"));
+                assert_eq!(from, 0);
+                assert_eq!(to, 100);
+            }
+            _ => panic!("expected Command::Export"),
+        }
+    }
+
+    #[test]
+    fn revert_subcommand_builds_command_revert() {
+        let matches = build_app().get_matches_from(vec!["t2node", "revert", "--to-height", "42"]);
+
+        match command_from_matches(&matches) {
+            Command::Revert { to_height, .. } => assert_eq!(to_height, 42),
+            _ => panic!("expected Command::Revert"),
+        }
+    }
+
+    #[test]
+    fn export_rejects_non_numeric_to_as_clap_usage_error() {
+        let result = build_app().get_matches_from_safe(vec![
+            "t2node",
+            "export",
+            "--dst",
+            "out.dat",
+            "--to",
+            "not-a-number",
+        ]);
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn revert_rejects_non_numeric_to_height_as_clap_usage_error() {
+        let result = build_app().get_matches_from_safe(vec![
+            "t2node",
+            "revert",
+            "--to-height",
+            "not-a-number",
+        ]);
+
+        assert!(result.is_err());
+    }
 }